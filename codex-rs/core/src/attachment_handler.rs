@@ -6,75 +6,403 @@ use crate::error::Result;
 use crate::error::CodexErr;
 use mime_guess::from_path;
 use serde_json::json;
+use sha2::{Digest, Sha256};
 use tracing::debug;
+use url::Url;
+
+use crate::file_path_detection::REMOTE_FETCH_SIZE_LIMIT;
+
+const SHA2_256_MULTIHASH_CODE: u8 = 0x12;
+const SHA2_256_DIGEST_LEN: u8 = 0x20;
+const BASE32_LOWER_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+fn multihash_sha256(content: &[u8]) -> Vec<u8> {
+    let digest = Sha256::digest(content);
+    let mut multihash = Vec::with_capacity(2 + digest.len());
+    // sha2-256 multihash: function code 0x12, digest length 0x20.
+    multihash.push(SHA2_256_MULTIHASH_CODE);
+    multihash.push(SHA2_256_DIGEST_LEN);
+    multihash.extend_from_slice(&digest);
+    multihash
+}
+
+fn encode_base32_lower(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity((bytes.len() * 8 + 4) / 5);
+    let mut buffer: u32 = 0;
+    let mut bits_left = 0;
+
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits_left += 8;
+        while bits_left >= 5 {
+            bits_left -= 5;
+            output.push(BASE32_LOWER_ALPHABET[((buffer >> bits_left) & 0x1f) as usize] as char);
+        }
+    }
+    if bits_left > 0 {
+        output.push(BASE32_LOWER_ALPHABET[((buffer << (5 - bits_left)) & 0x1f) as usize] as char);
+    }
+
+    output
+}
+
+fn multibase_encode(bytes: &[u8]) -> String {
+    format!("b{}", encode_base32_lower(bytes))
+}
+
+pub fn content_address(content: &[u8]) -> String {
+    multibase_encode(&multihash_sha256(content))
+}
+
+const COMPRESSION_THRESHOLD: usize = 64 * 1024;
+
+fn compress_for_attachment(content: &[u8]) -> (Vec<u8>, Option<&'static str>) {
+    if content.len() < COMPRESSION_THRESHOLD {
+        return (content.to_vec(), None);
+    }
+
+    if let Ok(compressed) = zstd::encode_all(content, 0) {
+        if compressed.len() < content.len() {
+            return (compressed, Some("zstd"));
+        }
+    }
+
+    if let Ok(compressed) = gzip_compress(content) {
+        if compressed.len() < content.len() {
+            return (compressed, Some("gzip"));
+        }
+    }
+
+    (content.to_vec(), None)
+}
+
+fn gzip_compress(content: &[u8]) -> std::io::Result<Vec<u8>> {
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content)?;
+    encoder.finish()
+}
+
+fn decompress_for_attachment(content: &[u8], codec: Option<&str>) -> Result<Vec<u8>> {
+    match codec {
+        None => Ok(content.to_vec()),
+        Some("zstd") => zstd::decode_all(content)
+            .map_err(|e| CodexErr::Other(format!("Failed to decompress zstd attachment: {e}"))),
+        Some("gzip") => {
+            use flate2::read::GzDecoder;
+            use std::io::Read;
+
+            let mut decoder = GzDecoder::new(content);
+            let mut decompressed = Vec::new();
+            decoder
+                .read_to_end(&mut decompressed)
+                .map_err(|e| CodexErr::Other(format!("Failed to decompress gzip attachment: {e}")))?;
+            Ok(decompressed)
+        }
+        Some(other) => Err(CodexErr::Other(format!("Unknown attachment codec: {other}"))),
+    }
+}
+
+fn bytes_to_data_url(content: &[u8], mime_type: &str) -> (String, Option<&'static str>) {
+    let (payload, codec) = compress_for_attachment(content);
+    let encoded = general_purpose::STANDARD.encode(&payload);
+
+    let data_url = match codec {
+        Some(codec) => format!("data:{mime_type};codec={codec};base64,{encoded}"),
+        None => format!("data:{mime_type};base64,{encoded}"),
+    };
+
+    (data_url, codec)
+}
 
 pub fn file_to_data_url(path: &Path) -> Result<String> {
     let file_content = std::fs::read(path)?;
-    let encoded = general_purpose::STANDARD.encode(&file_content);
-    
+
     let mime_type = from_path(path)
         .first_or_octet_stream()
         .to_string();
-    
-    Ok(format!("data:{};base64,{}", mime_type, encoded))
+
+    let (data_url, _codec) = bytes_to_data_url(&file_content, &mime_type);
+    Ok(data_url)
 }
 
 pub fn file_to_attachment(path: &Path) -> Result<serde_json::Value> {
-    let data_url = file_to_data_url(path)?;
+    let file_content = std::fs::read(path)?;
+    let addr = content_address(&file_content);
+
+    let mime_type = from_path(path)
+        .first_or_octet_stream()
+        .to_string();
+    let (data_url, codec) = bytes_to_data_url(&file_content, &mime_type);
+
     let filename = path.file_name()
         .and_then(|name| name.to_str())
         .ok_or_else(|| CodexErr::Other("Invalid file name".to_string()))?;
-    
-    Ok(json!({
+
+    let mut attachment = json!({
         "type": "file",
         "name": filename,
-        "content": data_url
-    }))
+        "content": data_url,
+        "addr": addr
+    });
+    if let Some(codec) = codec {
+        attachment["encoding"] = json!(codec);
+    }
+
+    Ok(attachment)
+}
+
+// Strips any parameters (e.g. `; charset=utf-8`) off a server-supplied
+// Content-Type so it can't smuggle a `codec=` segment into the MIME chain
+// that `bytes_to_data_url` appends our own compression marker to.
+fn bare_mime_type(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or(content_type).trim().to_string()
+}
+
+pub async fn url_to_attachment(url: &Url) -> Result<serde_json::Value> {
+    use futures_util::StreamExt;
+
+    let response = reqwest::Client::new()
+        .get(url.as_str())
+        .send()
+        .await
+        .map_err(|e| CodexErr::Other(format!("Failed to fetch {url}: {e}")))?;
+
+    if let Some(len) = response.content_length() {
+        if len > REMOTE_FETCH_SIZE_LIMIT {
+            return Err(CodexErr::Other(format!(
+                "Remote file at {url} is {len} bytes, exceeding the {REMOTE_FETCH_SIZE_LIMIT} byte limit"
+            )));
+        }
+    }
+
+    let content_type = bare_mime_type(
+        response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("application/octet-stream"),
+    );
+
+    let mut bytes = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| CodexErr::Other(format!("Failed to read response body from {url}: {e}")))?;
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() as u64 > REMOTE_FETCH_SIZE_LIMIT {
+            return Err(CodexErr::Other(format!(
+                "Remote file at {url} exceeds the {REMOTE_FETCH_SIZE_LIMIT} byte limit"
+            )));
+        }
+    }
+
+    let addr = content_address(&bytes);
+    let filename = filename_from_url(url, &content_type);
+    let (data_url, codec) = bytes_to_data_url(&bytes, &content_type);
+
+    let mut attachment = json!({
+        "type": "file",
+        "name": filename,
+        "content": data_url,
+        "addr": addr
+    });
+    if let Some(codec) = codec {
+        attachment["encoding"] = json!(codec);
+    }
+
+    Ok(attachment)
+}
+
+fn filename_from_url(url: &Url, content_type: &str) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.rfind(|segment: &&str| !segment.is_empty()).map(str::to_string))
+        .unwrap_or_else(|| {
+            let ext = mime_guess::get_mime_extensions_str(content_type)
+                .and_then(|exts| exts.first())
+                .copied()
+                .unwrap_or("bin");
+            format!("download.{ext}")
+        })
 }
 
 pub fn files_to_attachments(paths: &[PathBuf]) -> Result<Vec<serde_json::Value>> {
     let mut attachments = Vec::with_capacity(paths.len());
-    
+
     for path in paths {
         let attachment = file_to_attachment(path)?;
         attachments.push(attachment);
     }
-    
+
     Ok(attachments)
 }
 
-pub fn save_data_url_to_file(data_url: &str, filename: &str, save_dir: &Path) -> Result<PathBuf> {
-    debug!("Saving data URL to file: {}", filename);
-    
-    let parts: Vec<&str> = data_url.split(';').collect();
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct CborAttachment {
+    name: String,
+    mime: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    codec: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    addr: Option<String>,
+    content: serde_bytes::ByteBuf,
+}
+
+impl CborAttachment {
+    fn from_json(attachment: &serde_json::Value) -> Result<Self> {
+        let name = attachment["name"].as_str()
+            .ok_or_else(|| CodexErr::Other("Attachment missing name field".to_string()))?
+            .to_string();
+        let content = attachment["content"].as_str()
+            .ok_or_else(|| CodexErr::Other("Attachment missing content field".to_string()))?;
+        let parsed = parse_data_url(content)?;
+        let addr = attachment["addr"].as_str().map(str::to_string);
+
+        Ok(CborAttachment {
+            name,
+            mime: parsed.mime,
+            codec: parsed.codec,
+            addr,
+            content: serde_bytes::ByteBuf::from(parsed.bytes),
+        })
+    }
+
+    fn into_json(self) -> serde_json::Value {
+        let encoded = general_purpose::STANDARD.encode(&self.content);
+        let data_url = match &self.codec {
+            Some(codec) => format!("data:{};codec={};base64,{}", self.mime, codec, encoded),
+            None => format!("data:{};base64,{}", self.mime, encoded),
+        };
+
+        let mut attachment = json!({
+            "type": "file",
+            "name": self.name,
+            "content": data_url
+        });
+        if let Some(addr) = self.addr {
+            attachment["addr"] = json!(addr);
+        }
+
+        attachment
+    }
+}
+
+pub fn attachment_to_cbor(attachment: &serde_json::Value) -> Result<Vec<u8>> {
+    let cbor_attachment = CborAttachment::from_json(attachment)?;
+
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(&cbor_attachment, &mut buf)
+        .map_err(|e| CodexErr::Other(format!("Failed to encode attachment as CBOR: {e}")))?;
+    Ok(buf)
+}
+
+pub fn attachment_from_cbor(bytes: &[u8]) -> Result<serde_json::Value> {
+    let cbor_attachment: CborAttachment = ciborium::de::from_reader(bytes)
+        .map_err(|e| CodexErr::Other(format!("Failed to decode CBOR attachment: {e}")))?;
+
+    Ok(cbor_attachment.into_json())
+}
+
+pub fn attachments_to_cbor(attachments: &[serde_json::Value]) -> Result<Vec<u8>> {
+    let cbor_attachments = attachments
+        .iter()
+        .map(CborAttachment::from_json)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(&cbor_attachments, &mut buf)
+        .map_err(|e| CodexErr::Other(format!("Failed to encode attachments as CBOR: {e}")))?;
+    Ok(buf)
+}
+
+pub fn attachments_from_cbor(bytes: &[u8]) -> Result<Vec<serde_json::Value>> {
+    let cbor_attachments: Vec<CborAttachment> = ciborium::de::from_reader(bytes)
+        .map_err(|e| CodexErr::Other(format!("Failed to decode CBOR attachments: {e}")))?;
+
+    Ok(cbor_attachments.into_iter().map(CborAttachment::into_json).collect())
+}
+
+pub fn cbor_to_file(cbor_bytes: &[u8], save_dir: &Path) -> Result<PathBuf> {
+    let cbor_attachment: CborAttachment = ciborium::de::from_reader(cbor_bytes)
+        .map_err(|e| CodexErr::Other(format!("Failed to decode CBOR attachment: {e}")))?;
+
+    let decoded = decompress_for_attachment(&cbor_attachment.content, cbor_attachment.codec.as_deref())?;
+
+    store_content_addressed(save_dir, &decoded)
+}
+
+struct ParsedDataUrl {
+    mime: String,
+    codec: Option<String>,
+    bytes: Vec<u8>,
+}
+
+fn parse_data_url(data_url: &str) -> Result<ParsedDataUrl> {
+    let parts: Vec<&str> = data_url.splitn(2, ',').collect();
     if parts.len() < 2 {
         return Err(CodexErr::Other(format!("Invalid data URL format: {}", data_url)));
     }
-    
-    let mime_parts: Vec<&str> = parts[0].split(':').collect();
+
+    let mut header_segments = parts[0].split(';');
+    let mime_parts: Vec<&str> = header_segments
+        .next()
+        .ok_or_else(|| CodexErr::Other(format!("Invalid data URL format: {}", data_url)))?
+        .splitn(2, ':')
+        .collect();
     if mime_parts.len() < 2 {
         return Err(CodexErr::Other(format!("Invalid MIME type in data URL: {}", data_url)));
     }
-    
-    let base64_parts: Vec<&str> = parts[1].split(',').collect();
-    if base64_parts.len() < 2 {
-        return Err(CodexErr::Other(format!("Invalid base64 data in data URL: {}", data_url)));
-    }
-    
-    let decoded = general_purpose::STANDARD.decode(base64_parts[1])?;
-    
+
+    let codec = header_segments
+        .find_map(|segment| segment.strip_prefix("codec="))
+        .map(str::to_string);
+
+    let bytes = general_purpose::STANDARD.decode(parts[1])?;
+
+    Ok(ParsedDataUrl {
+        mime: mime_parts[1].to_string(),
+        codec,
+        bytes,
+    })
+}
+
+// Stored purely by content address (no extension), so re-uploading the
+// same bytes under a different filename/extension always dedupes onto
+// the same path instead of writing a second copy.
+fn store_content_addressed(save_dir: &Path, decoded: &[u8]) -> Result<PathBuf> {
     std::fs::create_dir_all(save_dir)?;
-    
-    let file_path = save_dir.join(filename);
-    
+
+    let addr = content_address(decoded);
+    let file_path = save_dir.join(&addr);
+
+    if file_path.exists() {
+        debug!("Attachment {} already stored at {:?}, skipping write", addr, file_path);
+        return Ok(file_path);
+    }
+
     let mut file = std::fs::File::create(&file_path)?;
-    file.write_all(&decoded)?;
-    
+    file.write_all(decoded)?;
+
     debug!("Saved attachment to: {:?}", file_path);
-    
+
     Ok(file_path)
 }
 
+pub fn save_data_url_to_file(data_url: &str, filename_hint: &str, save_dir: &Path) -> Result<PathBuf> {
+    debug!("Saving data URL to file: {}", filename_hint);
+
+    let parsed = parse_data_url(data_url)?;
+    let decoded = decompress_for_attachment(&parsed.bytes, parsed.codec.as_deref())?;
+
+    store_content_addressed(save_dir, &decoded)
+}
+
+pub fn resolve_attachment_by_addr(dir: &Path, addr: &str) -> Option<PathBuf> {
+    let path = dir.join(addr);
+    path.exists().then_some(path)
+}
+
 pub fn process_attachment(attachment: &serde_json::Value, save_dir: &Path) -> Result<PathBuf> {
     let content = attachment["content"].as_str()
         .ok_or_else(|| CodexErr::Other("Attachment missing content field".to_string()))?;
@@ -96,7 +424,136 @@ pub fn process_attachments(attachments: &[serde_json::Value], save_dir: &Path) -
     Ok(file_paths)
 }
 
+// Accepts either a JSON array of data-URL attachments or a CBOR envelope.
+pub fn process_attachments_auto(data: &[u8], save_dir: &Path) -> Result<Vec<PathBuf>> {
+    if let Ok(attachments) = serde_json::from_slice::<Vec<serde_json::Value>>(data) {
+        return process_attachments(&attachments, save_dir);
+    }
+
+    if let Ok(attachment) = serde_json::from_slice::<serde_json::Value>(data) {
+        return Ok(vec![process_attachment(&attachment, save_dir)?]);
+    }
+
+    if let Ok(attachments) = attachments_from_cbor(data) {
+        return process_attachments(&attachments, save_dir);
+    }
+
+    Ok(vec![cbor_to_file(data, save_dir)?])
+}
+
 pub fn default_attachment_dir() -> PathBuf {
     let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
     home_dir.join(".codex").join("attachments")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_random_bytes(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        let mut out = Vec::with_capacity(len);
+        for _ in 0..len {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            out.push((state & 0xff) as u8);
+        }
+        out
+    }
+
+    #[test]
+    fn compress_round_trip_zstd() {
+        let content = vec![b'a'; COMPRESSION_THRESHOLD * 2];
+        let (compressed, codec) = compress_for_attachment(&content);
+        assert_eq!(codec, Some("zstd"));
+        assert!(compressed.len() < content.len());
+
+        let decompressed = decompress_for_attachment(&compressed, codec).unwrap();
+        assert_eq!(decompressed, content);
+    }
+
+    #[test]
+    fn compress_round_trip_gzip() {
+        let content = vec![b'b'; COMPRESSION_THRESHOLD * 2];
+        let compressed = gzip_compress(&content).unwrap();
+        assert!(compressed.len() < content.len());
+
+        let decompressed = decompress_for_attachment(&compressed, Some("gzip")).unwrap();
+        assert_eq!(decompressed, content);
+    }
+
+    #[test]
+    fn no_compression_round_trip_for_small_content() {
+        let content = b"too small to compress".to_vec();
+        let (payload, codec) = compress_for_attachment(&content);
+        assert_eq!(codec, None);
+        assert_eq!(payload, content);
+
+        let decompressed = decompress_for_attachment(&payload, codec).unwrap();
+        assert_eq!(decompressed, content);
+    }
+
+    #[test]
+    fn compression_falls_back_to_raw_when_it_does_not_shrink() {
+        let content = pseudo_random_bytes(COMPRESSION_THRESHOLD * 2, 0x1234_5678_9abc_def0);
+        let (payload, codec) = compress_for_attachment(&content);
+        assert_eq!(codec, None);
+        assert_eq!(payload, content);
+    }
+
+    #[test]
+    fn data_url_round_trips_through_save_data_url_to_file() {
+        let content = vec![b'c'; COMPRESSION_THRESHOLD * 2];
+        let (data_url, codec) = bytes_to_data_url(&content, "text/plain");
+        assert_eq!(codec, Some("zstd"));
+        assert!(data_url.contains("codec=zstd"));
+
+        let dir = std::env::temp_dir().join(format!("attachment_handler_test_{}", std::process::id()));
+        let path = save_data_url_to_file(&data_url, "round-trip.txt", &dir).unwrap();
+        let saved = std::fs::read(&path).unwrap();
+        assert_eq!(saved, content);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn same_content_dedupes_across_different_filenames() {
+        let content = b"duplicate bytes, different names".to_vec();
+        let (data_url, _codec) = bytes_to_data_url(&content, "text/plain");
+
+        let dir = std::env::temp_dir().join(format!("attachment_handler_dedup_test_{}", std::process::id()));
+        let first = save_data_url_to_file(&data_url, "note.txt", &dir).unwrap();
+        let second = save_data_url_to_file(&data_url, "note.log", &dir).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let addr = content_address(&content);
+        assert_eq!(resolve_attachment_by_addr(&dir, &addr), Some(first));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn cbor_round_trip_preserves_name_content_and_addr() {
+        let content = b"cbor envelope round trip".to_vec();
+        let addr = content_address(&content);
+        let (data_url, _codec) = bytes_to_data_url(&content, "text/plain");
+
+        let attachment = json!({
+            "type": "file",
+            "name": "notes.txt",
+            "content": data_url,
+            "addr": addr
+        });
+
+        let cbor_bytes = attachment_to_cbor(&attachment).unwrap();
+        let round_tripped = attachment_from_cbor(&cbor_bytes).unwrap();
+
+        assert_eq!(round_tripped["name"], "notes.txt");
+        assert_eq!(round_tripped["addr"], addr);
+
+        let parsed = parse_data_url(round_tripped["content"].as_str().unwrap()).unwrap();
+        assert_eq!(parsed.bytes, content);
+    }
+}
@@ -4,6 +4,7 @@
 use std::path::{Path, PathBuf};
 use regex::Regex;
 use lazy_static::lazy_static;
+use url::Url;
 
 lazy_static! {
     static ref UNIX_PATH_REGEX: Regex = Regex::new(r"/[a-zA-Z0-9_.-/]+").unwrap();
@@ -13,6 +14,8 @@ lazy_static! {
     static ref URL_REGEX: Regex = Regex::new(r"https?://[\w\./\?\-_%&=]+").unwrap();
 }
 
+pub const REMOTE_FETCH_SIZE_LIMIT: u64 = 10 * 1024 * 1024;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FilePathHandlingOption {
     Upload,
@@ -20,6 +23,12 @@ pub enum FilePathHandlingOption {
     Cancel,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteUrlHandlingOption {
+    FetchAndAttach,
+    PassThrough,
+}
+
 #[derive(Debug, Clone)]
 pub struct FilePathDetectionResult {
     pub original_input: String,
@@ -76,6 +85,13 @@ pub fn detect_local_file_paths(input: &str) -> Vec<PathBuf> {
     paths
 }
 
+pub fn detect_remote_urls(input: &str) -> Vec<Url> {
+    URL_REGEX
+        .find_iter(input)
+        .filter_map(|m| Url::parse(m.as_str()).ok())
+        .collect()
+}
+
 pub fn substitute_file_paths_with_urls(
     input: &str,
     path_url_map: &[(PathBuf, String)],
@@ -108,14 +124,18 @@ pub fn prompt_for_file_path_handling(paths: &[PathBuf]) -> FilePathHandlingOptio
     FilePathHandlingOption::Upload
 }
 
+pub fn prompt_for_remote_url_handling(urls: &[Url]) -> RemoteUrlHandlingOption {
+    RemoteUrlHandlingOption::FetchAndAttach
+}
+
 pub fn should_process_remotely(path: &Path) -> bool {
     if !path.is_file() {
         return false;
     }
-    
+
     match std::fs::metadata(path) {
         Ok(metadata) => {
-            metadata.len() < 10 * 1024 * 1024
+            metadata.len() < REMOTE_FETCH_SIZE_LIMIT
         }
         Err(_) => false,
     }
@@ -3,6 +3,8 @@ use serde::Deserialize;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::env::VarError;
+use std::sync::Mutex;
+use std::sync::OnceLock;
 
 use crate::error::EnvVarError;
 
@@ -15,6 +17,16 @@ pub enum WireApi {
     Devin,
 }
 
+// How `list_models` discovers a provider's models. Keyed explicitly rather
+// than inferred from `name`, since user config can name a provider anything.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelDiscoveryShape {
+    #[default]
+    OpenAi,
+    Ollama,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct ModelProviderInfo {
     pub name: String,
@@ -24,9 +36,94 @@ pub struct ModelProviderInfo {
     pub env_key_instructions: Option<String>,
 
     pub wire_api: WireApi,
+
+    #[serde(default)]
+    pub model_discovery: ModelDiscoveryShape,
+
+    // Values may reference `${ENV_VAR}`.
+    #[serde(default)]
+    pub http_headers: HashMap<String, String>,
+
+    // Values may reference `${ENV_VAR}`.
+    #[serde(default)]
+    pub query_params: HashMap<String, String>,
+
+    // Defaults to "Authorization".
+    #[serde(default)]
+    pub auth_header: Option<String>,
+
+    // Defaults to "Bearer"; empty string sends the key with no prefix.
+    #[serde(default)]
+    pub auth_scheme: Option<String>,
 }
 
+static MODEL_LIST_CACHE: OnceLock<Mutex<HashMap<String, Vec<String>>>> = OnceLock::new();
+
 impl ModelProviderInfo {
+    // Cached per provider `base_url` for the process lifetime, not `name`
+    // (user config can give two distinct providers the same display name);
+    // only a confirmed response is cached, so a transient failure is retried
+    // on the next call instead of sticking as an empty list.
+    pub async fn list_models(&self) -> crate::error::Result<Vec<String>> {
+        let cache = MODEL_LIST_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+        if let Some(cached) = cache.lock().unwrap().get(&self.base_url) {
+            return Ok(cached.clone());
+        }
+
+        let Some(models) = self.fetch_models().await else {
+            return Ok(Vec::new());
+        };
+        cache.lock().unwrap().insert(self.base_url.clone(), models.clone());
+        Ok(models)
+    }
+
+    async fn fetch_models(&self) -> Option<Vec<String>> {
+        let client = reqwest::Client::new();
+
+        if self.model_discovery == ModelDiscoveryShape::Ollama {
+            return self.fetch_ollama_models(&client).await;
+        }
+
+        let url = format!("{}/models", self.base_url.trim_end_matches('/'));
+        let mut request = client.get(&url);
+        if let Ok(Some((header_name, header_value))) = self.auth_header() {
+            request = request.header(header_name, header_value);
+        }
+
+        let response = request.send().await.ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let body = response.json::<serde_json::Value>().await.ok()?;
+
+        Some(
+            body["data"]
+                .as_array()?
+                .iter()
+                .filter_map(|entry| entry["id"].as_str().map(str::to_string))
+                .collect(),
+        )
+    }
+
+    async fn fetch_ollama_models(&self, client: &reqwest::Client) -> Option<Vec<String>> {
+        let base = self.base_url.trim_end_matches("/v1").trim_end_matches('/');
+        let url = format!("{base}/api/tags");
+
+        let response = client.get(&url).send().await.ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let body = response.json::<serde_json::Value>().await.ok()?;
+
+        Some(
+            body["models"]
+                .as_array()?
+                .iter()
+                .filter_map(|entry| entry["name"].as_str().map(str::to_string))
+                .collect(),
+        )
+    }
+
     pub fn api_key(&self) -> crate::error::Result<Option<String>> {
         match &self.env_key {
             Some(env_key) => std::env::var(env_key)
@@ -46,6 +143,77 @@ impl ModelProviderInfo {
             None => Ok(None),
         }
     }
+
+    pub fn auth_header(&self) -> crate::error::Result<Option<(String, String)>> {
+        let Some(key) = self.api_key()? else {
+            return Ok(None);
+        };
+
+        let header_name = self.auth_header.clone().unwrap_or_else(|| "Authorization".to_string());
+        let value = match self.auth_scheme.as_deref() {
+            Some("") => key,
+            Some(scheme) => format!("{scheme} {key}"),
+            None => format!("Bearer {key}"),
+        };
+
+        Ok(Some((header_name, value)))
+    }
+
+    pub fn resolved_http_headers(&self) -> crate::error::Result<HashMap<String, String>> {
+        self.http_headers
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), interpolate_env_vars(value)?)))
+            .collect()
+    }
+
+    pub fn resolved_query_params(&self) -> crate::error::Result<HashMap<String, String>> {
+        self.query_params
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), interpolate_env_vars(value)?)))
+            .collect()
+    }
+}
+
+fn interpolate_env_vars(value: &str) -> crate::error::Result<String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let after_marker = &rest[start + 2..];
+        let end = after_marker.find('}').ok_or_else(|| {
+            crate::error::CodexErr::Other(format!("Unterminated ${{...}} in value: {value}"))
+        })?;
+
+        let var_name = &after_marker[..end];
+        let resolved = std::env::var(var_name).map_err(|_| {
+            crate::error::CodexErr::EnvVar(EnvVarError {
+                var: var_name.to_string(),
+                instructions: None,
+            })
+        })?;
+        result.push_str(&resolved);
+
+        rest = &after_marker[end + 1..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+// Entries in `path` win over built-ins on key collision.
+pub fn load_providers_from_config(path: &std::path::Path) -> crate::error::Result<HashMap<String, ModelProviderInfo>> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        crate::error::CodexErr::Other(format!("Failed to read provider config at {path:?}: {e}"))
+    })?;
+
+    let user_providers: HashMap<String, ModelProviderInfo> = toml::from_str(&contents).map_err(|e| {
+        crate::error::CodexErr::Other(format!("Failed to parse provider config at {path:?}: {e}"))
+    })?;
+
+    let mut providers = built_in_model_providers();
+    providers.extend(user_providers);
+    Ok(providers)
 }
 
 pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
@@ -60,6 +228,11 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("OPENAI_API_KEY".into()),
                 env_key_instructions: Some("Create an API key (https://platform.openai.com) and export it as an environment variable.".into()),
                 wire_api: WireApi::Responses,
+                model_discovery: ModelDiscoveryShape::OpenAi,
+                http_headers: HashMap::new(),
+                query_params: HashMap::new(),
+                auth_header: None,
+                auth_scheme: None,
             },
         ),
         (
@@ -70,6 +243,11 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("OPENROUTER_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                model_discovery: ModelDiscoveryShape::OpenAi,
+                http_headers: HashMap::new(),
+                query_params: HashMap::new(),
+                auth_header: None,
+                auth_scheme: None,
             },
         ),
         (
@@ -80,6 +258,11 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("GEMINI_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                model_discovery: ModelDiscoveryShape::OpenAi,
+                http_headers: HashMap::new(),
+                query_params: HashMap::new(),
+                auth_header: None,
+                auth_scheme: None,
             },
         ),
         (
@@ -90,6 +273,11 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: None,
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                model_discovery: ModelDiscoveryShape::Ollama,
+                http_headers: HashMap::new(),
+                query_params: HashMap::new(),
+                auth_header: None,
+                auth_scheme: None,
             },
         ),
         (
@@ -100,6 +288,11 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("MISTRAL_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                model_discovery: ModelDiscoveryShape::OpenAi,
+                http_headers: HashMap::new(),
+                query_params: HashMap::new(),
+                auth_header: None,
+                auth_scheme: None,
             },
         ),
         (
@@ -110,6 +303,11 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("DEEPSEEK_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                model_discovery: ModelDiscoveryShape::OpenAi,
+                http_headers: HashMap::new(),
+                query_params: HashMap::new(),
+                auth_header: None,
+                auth_scheme: None,
             },
         ),
         (
@@ -120,6 +318,11 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("XAI_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                model_discovery: ModelDiscoveryShape::OpenAi,
+                http_headers: HashMap::new(),
+                query_params: HashMap::new(),
+                auth_header: None,
+                auth_scheme: None,
             },
         ),
         (
@@ -130,6 +333,26 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("GROQ_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                model_discovery: ModelDiscoveryShape::OpenAi,
+                http_headers: HashMap::new(),
+                query_params: HashMap::new(),
+                auth_header: None,
+                auth_scheme: None,
+            },
+        ),
+        (
+            "huggingface",
+            P {
+                name: "Hugging Face".into(),
+                base_url: "https://router.huggingface.co/v1".into(),
+                env_key: Some("HF_TOKEN".into()),
+                env_key_instructions: Some("Create a Hugging Face access token (https://huggingface.co/settings/tokens) and export it as an environment variable.".into()),
+                wire_api: WireApi::Chat,
+                model_discovery: ModelDiscoveryShape::OpenAi,
+                http_headers: HashMap::new(),
+                query_params: HashMap::new(),
+                auth_header: None,
+                auth_scheme: None,
             },
         ),
         (
@@ -140,6 +363,11 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("DEVIN_API_KEY".into()),
                 env_key_instructions: Some("Create a Devin API key (https://docs.devin.ai/api-reference) and export it as an environment variable.".into()),
                 wire_api: WireApi::Devin,
+                model_discovery: ModelDiscoveryShape::OpenAi,
+                http_headers: HashMap::new(),
+                query_params: HashMap::new(),
+                auth_header: None,
+                auth_scheme: None,
             },
         ),
     ]
@@ -147,3 +375,56 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
     .map(|(k, v)| (k.to_string(), v))
     .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_providers_from_config_overrides_built_ins_and_adds_new_ones() {
+        let dir = std::env::temp_dir().join(format!("model_provider_info_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("providers.toml");
+        std::fs::write(
+            &config_path,
+            r#"
+[openai]
+name = "OpenAI (self-hosted)"
+base_url = "https://openai.internal.example.com/v1"
+env_key = "OPENAI_API_KEY"
+env_key_instructions = ""
+wire_api = "responses"
+
+[acme]
+name = "Acme"
+base_url = "https://acme.example.com/v1"
+env_key = "ACME_API_KEY"
+env_key_instructions = ""
+wire_api = "chat"
+"#,
+        )
+        .unwrap();
+
+        let providers = load_providers_from_config(&config_path).unwrap();
+
+        assert_eq!(providers["openai"].name, "OpenAI (self-hosted)");
+        assert_eq!(providers["openai"].base_url, "https://openai.internal.example.com/v1");
+        assert_eq!(providers["acme"].name, "Acme");
+        assert_eq!(providers["gemini"].name, "Gemini");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn interpolate_env_vars_errors_on_missing_var() {
+        let var_name = "CODEX_TEST_MISSING_ENV_VAR_FOR_INTERPOLATION";
+        std::env::remove_var(var_name);
+
+        let err = interpolate_env_vars(&format!("prefix-${{{var_name}}}-suffix")).unwrap_err();
+
+        match err {
+            crate::error::CodexErr::EnvVar(e) => assert_eq!(e.var, var_name),
+            _ => panic!("expected EnvVar error"),
+        }
+    }
+}